@@ -0,0 +1,102 @@
+#[derive(Debug)]
+pub struct Ast<'l> {
+	pub program: Program,
+	pub symbols: Vec<&'l str>,
+}
+
+#[derive(Debug)]
+pub struct Program {
+	pub block: Block,
+}
+
+#[derive(Debug)]
+pub struct Block {
+	pub consts: Vec<ConstDecl>,
+	pub vars: Vec<usize>,
+	pub procedures: Vec<ProcedureDecl>,
+	pub statement: Statement,
+}
+
+#[derive(Debug)]
+pub struct ConstDecl {
+	pub name: usize,
+	pub value: Literal,
+}
+
+#[derive(Debug)]
+pub struct ProcedureDecl {
+	pub name: usize,
+	pub block: Box<Block>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Literal {
+	Int(u32),
+	Decimal(f64),
+}
+
+#[derive(Debug)]
+pub enum Statement {
+	Assign { name: usize, value: Expression },
+	Call { name: usize },
+	Begin(Vec<Statement>),
+	If { condition: Condition, then_branch: Box<Statement> },
+	While { condition: Condition, body: Box<Statement> },
+	Read { name: usize },
+	Write { value: Expression },
+	Empty,
+}
+
+#[derive(Debug)]
+pub enum Condition {
+	Odd(Expression),
+	Compare { lhs: Expression, op: CompareOp, rhs: Expression },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CompareOp {
+	Equal,
+	NotEqual,
+	Less,
+	LessEqual,
+	Greater,
+	GreaterEqual,
+}
+
+#[derive(Debug)]
+pub struct Expression {
+	pub negate: bool,
+	pub first: Term,
+	pub rest: Vec<(AddOp, Term)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AddOp {
+	Add,
+	Sub,
+}
+
+#[derive(Debug)]
+pub struct Term {
+	pub first: Factor,
+	pub rest: Vec<(MulOp, Factor)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MulOp {
+	Mul,
+	Div,
+}
+
+#[derive(Debug)]
+pub enum Factor {
+	Ident(usize),
+	Literal(Literal),
+	Paren(Box<Expression>),
+}
+
+impl<'l> Ast<'l> {
+	pub fn new(program: Program, symbols: Vec<&'l str>) -> Self {
+		Self { program, symbols }
+	}
+}
@@ -1,20 +1,21 @@
+use std::borrow::Cow;
 use std::ops::Bound;
 
 #[derive(Debug)]
-pub struct Text {
-	symbols: Vec<String>,
-	tokens: Vec<Token>,
+pub struct Text<'l> {
+	symbols: Vec<&'l str>,
+	tokens: Vec<Token<'l>>,
 }
 
 #[derive(Debug)]
-pub struct Token {
-	lexeme: Lexeme,
+pub struct Token<'l> {
+	lexeme: Lexeme<'l>,
 	start: Position,
 	end: Bound<Position>,
 }
 
-#[derive(Debug)]
-pub enum Lexeme {
+#[derive(Debug, PartialEq)]
+pub enum Lexeme<'l> {
 	SignExclamation,
 	SignNumber,
 	SignLParen,
@@ -45,27 +46,50 @@ pub enum Lexeme {
 	KwVar,
 	KwWhile,
 	Ident(usize),
-	String(String),
+	String(Cow<'l, str>),
 	Number32(u32),
 	Decimal64(f64),
-	Error,
+	Error(LexError),
 	Eof,
 }
 
+/// A lexical failure recorded on the offending token instead of aborting
+/// the scan; callers collect and render all diagnostics at once.
+#[derive(Debug, PartialEq)]
+pub enum LexError {
+	UnexpectedChar(char),
+	MalformedNumber,
+	UnterminatedString,
+	UnterminatedBlockComment,
+	MalformedEscapeSequence,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Position {
 	pub col: usize,
 	pub line: usize,
 }
 
-impl Text {
-	pub fn new(tokens: Vec<Token>, symbols: Vec<String>) -> Self {
+impl<'l> Text<'l> {
+	pub fn new(tokens: Vec<Token<'l>>, symbols: Vec<&'l str>) -> Self {
 		Self { symbols, tokens }
 	}
 }
 
-impl Token {
-	pub fn new(lexeme: Lexeme, start: Position, end: Bound<Position>) -> Self {
+impl<'l> Token<'l> {
+	pub fn new(lexeme: Lexeme<'l>, start: Position, end: Bound<Position>) -> Self {
 		Self { lexeme, start, end }
 	}
+
+	pub fn lexeme(&self) -> &Lexeme<'l> {
+		&self.lexeme
+	}
+
+	pub fn start(&self) -> Position {
+		self.start
+	}
+
+	pub fn end(&self) -> Bound<Position> {
+		self.end
+	}
 }
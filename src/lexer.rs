@@ -1,11 +1,17 @@
-use super::lang::{Lexeme, Position, Text, Token};
+use super::lang::{LexError, Lexeme, Position, Text, Token};
 use fsm::Symbols;
+use std::borrow::Cow;
 use std::ops::{Bound, Range};
+use std::str::CharIndices;
 
 pub struct Lexer<'l> {
 	text: &'l str,
-	tokens: Vec<Token>,
-	symbols: Symbols<String>,
+	chars: CharIndices<'l>,
+	pending: Option<(usize, char)>,
+	pending_out: Option<Token<'l>>,
+	ended: bool,
+	done: bool,
+	symbols: Symbols<&'l str>,
 	state: State,
 	token_start: Position,
 	pos: Position,
@@ -17,47 +23,134 @@ enum State {
 	BlockCommentAst,
 	LineComment,
 	Operator(char),
+	Dot(usize),
 	Ident(usize),
 	String(usize),
-	StringEscSeq(usize),
+	StringOwned(String, Option<LexError>),
+	StringEscSeq(String, Option<LexError>),
+	StringEscHex(String, Option<LexError>, String),
+	StringEscUnicodeOpen(String, Option<LexError>),
+	StringEscUnicode(String, Option<LexError>, String),
 	Number(usize, u32),
 	Decimal(usize),
+	DecimalExp(usize),
+	DecimalExpSigned(usize),
+	DecimalExpDigits(usize),
 }
 
 impl<'l> Lexer<'l> {
-	pub fn parse(text: &'l str) -> Text {
-		let mut lexer = Self {
+	pub fn new(text: &'l str) -> Self {
+		Self {
 			text,
-			tokens: Vec::new(),
+			chars: text.char_indices(),
+			pending: None,
+			pending_out: None,
+			ended: false,
+			done: false,
 			symbols: Symbols::new(),
 			state: State::Common,
 			token_start: Position { col: 0, line: 0 },
 			pos: Position { col: 1, line: 1 },
-		};
-		for (index, c) in text.char_indices() {
-			while !lexer.next(c, index) {}
-			if c == '\n' {
-				lexer.pos.col = 0;
-				lexer.pos.line += 1;
+		}
+	}
+
+	pub fn parse(text: &'l str) -> Text<'l> {
+		let mut lexer = Self::new(text);
+		let tokens = lexer.by_ref().collect();
+		Text::new(tokens, lexer.symbols.into_table())
+	}
+
+	/// Hands over the symbol table accumulated so far. Intended for callers
+	/// (e.g. the parser) that drive the lexer via `next_token` directly
+	/// instead of going through `parse`.
+	pub fn into_symbol_table(self) -> Vec<&'l str> {
+		self.symbols.into_table()
+	}
+
+	/// Advances the state machine until exactly one `Token` is emitted
+	/// (including the final `Eof`). Calling this again after `Eof` has been
+	/// emitted keeps yielding fresh `Eof` tokens.
+	pub fn next_token(&mut self) -> Token<'l> {
+		loop {
+			let (index, c) = match self.pending.take() {
+				Some(pending) => pending,
+				None => match self.chars.next() {
+					Some(next) => next,
+					None => return self.finalize(),
+				},
+			};
+			if self.step(c, index) {
+				self.advance_pos(c);
+			} else {
+				self.pending = Some((index, c));
 			}
-			lexer.pos.col += 1;
+			if let Some(token) = self.pending_out.take() {
+				return token;
+			}
+		}
+	}
+
+	fn advance_pos(&mut self, c: char) {
+		if c == '\n' {
+			self.pos.col = 0;
+			self.pos.line += 1;
 		}
-		lexer.finalize();
-		Text::new(lexer.tokens, lexer.symbols.into_table())
+		self.pos.col += 1;
 	}
 
-	fn next(&mut self, c: char, index: usize) -> bool {
+	fn step(&mut self, c: char, index: usize) -> bool {
 		match self.state {
 			State::Common => self.next_common(c, index),
 			State::BlockComment => self.next_blk_comment(false, c),
 			State::BlockCommentAst => self.next_blk_comment(true, c),
 			State::LineComment => self.next_line_comment(c),
 			State::Operator(first) => self.next_op(first, c),
+			State::Dot(start) => self.next_dot(c, start),
 			State::Ident(start) => self.next_id(c, start..index),
-			State::String(start) => self.next_str(c, false, start..index),
-			State::StringEscSeq(start) => self.next_str(c, true, start..index),
+			State::String(start) => self.next_str_borrowed(c, start..index),
+			State::StringOwned(..) => {
+				let (buffer, error) = self.take_string_state();
+				self.next_str(c, buffer, error)
+			}
+			State::StringEscSeq(..) => {
+				let (buffer, error) = self.take_string_state();
+				self.next_str_esc(c, buffer, error)
+			}
+			State::StringEscHex(..) => {
+				let (buffer, error, hex) = self.take_string_hex_state();
+				self.next_str_hex(c, buffer, error, hex)
+			}
+			State::StringEscUnicodeOpen(..) => {
+				let (buffer, error) = self.take_string_state();
+				self.next_str_unicode_open(c, buffer, error)
+			}
+			State::StringEscUnicode(..) => {
+				let (buffer, error, hex) = self.take_string_hex_state();
+				self.next_str_unicode(c, buffer, error, hex)
+			}
 			State::Number(start, radix) => self.next_num(c, radix, start..index),
 			State::Decimal(start) => self.next_decimal(c, start..index),
+			State::DecimalExp(start) => self.next_decimal_exp(c, start),
+			State::DecimalExpSigned(start) => self.next_decimal_exp_signed(c, start),
+			State::DecimalExpDigits(start) => self.next_decimal_exp_digits(c, start..index),
+		}
+	}
+
+	fn take_string_state(&mut self) -> (String, Option<LexError>) {
+		match std::mem::replace(&mut self.state, State::Common) {
+			State::StringOwned(buffer, error)
+			| State::StringEscSeq(buffer, error)
+			| State::StringEscUnicodeOpen(buffer, error) => (buffer, error),
+			_ => unreachable!("take_string_state called outside a string state"),
+		}
+	}
+
+	fn take_string_hex_state(&mut self) -> (String, Option<LexError>, String) {
+		match std::mem::replace(&mut self.state, State::Common) {
+			State::StringEscHex(buffer, error, hex) | State::StringEscUnicode(buffer, error, hex) => {
+				(buffer, error, hex)
+			}
+			_ => unreachable!("take_string_hex_state called outside a string hex state"),
 		}
 	}
 
@@ -67,6 +160,7 @@ impl<'l> Lexer<'l> {
 			_ if c.is_whitespace() => return true,
 			'"' => State::String(index + 1),
 			'/' | ':' | '<' | '>' => State::Operator(c),
+			'.' => State::Dot(index),
 			'0' => State::Number(index, 0),
 			'1'..='9' => State::Number(index, 10),
 			_ if c.is_alphabetic() => State::Ident(index),
@@ -80,11 +174,10 @@ impl<'l> Lexer<'l> {
 					'+' => Lexeme::SignPlus,
 					',' => Lexeme::SignComma,
 					'-' => Lexeme::SignMinus,
-					'.' => Lexeme::SignFullStop,
 					';' => Lexeme::SignSemicolon,
 					'=' => Lexeme::SignEquals,
 					'?' => Lexeme::SignQuestion,
-					_ => Lexeme::Error,
+					_ => Lexeme::Error(LexError::UnexpectedChar(c)),
 				});
 			}
 		};
@@ -110,7 +203,7 @@ impl<'l> Lexer<'l> {
 					('<', _) => self.push_exclusive(Lexeme::OpLess),
 					('>', '=') => self.push_inclusive(Lexeme::OpGreaterEqual),
 					('>', _) => self.push_exclusive(Lexeme::OpGreater),
-					_ => self.push_exclusive(Lexeme::Error),
+					_ => self.push_exclusive(Lexeme::Error(LexError::UnexpectedChar(first))),
 				}
 			}
 		}
@@ -121,7 +214,8 @@ impl<'l> Lexer<'l> {
 			true
 		} else {
 			self.state = State::Common;
-			self.push_exclusive(match &self.text[index] {
+			let text = self.text;
+			self.push_exclusive(match &text[index] {
 				"begin" => Lexeme::KwBegin,
 				"call" => Lexeme::KwCall,
 				"const" => Lexeme::KwConst,
@@ -133,25 +227,127 @@ impl<'l> Lexer<'l> {
 				"then" => Lexeme::KwThen,
 				"var" => Lexeme::KwVar,
 				"while" => Lexeme::KwWhile,
-				id => return self.push_symbol(id.to_owned()),
+				id => return self.push_symbol(id),
 			})
 		}
 	}
 
-	fn next_str(&mut self, c: char, is_escaped: bool, index: Range<usize>) -> bool {
-		self.state = if is_escaped {
-			State::String(index.start)
+	fn next_str_borrowed(&mut self, c: char, index: Range<usize>) -> bool {
+		match c {
+			'"' => {
+				let text = self.text;
+				self.state = State::Common;
+				self.push_inclusive(Lexeme::String(Cow::Borrowed(&text[index])))
+			}
+			'\\' => {
+				self.state = State::StringEscSeq(self.text[index].to_owned(), None);
+				true
+			}
+			_ => true,
+		}
+	}
+
+	fn next_str(&mut self, c: char, mut buffer: String, error: Option<LexError>) -> bool {
+		match c {
+			'"' => self.push_inclusive(match error {
+				Some(error) => Lexeme::Error(error),
+				None => Lexeme::String(Cow::Owned(buffer)),
+			}),
+			'\\' => {
+				self.state = State::StringEscSeq(buffer, error);
+				true
+			}
+			_ => {
+				buffer.push(c);
+				self.state = State::StringOwned(buffer, error);
+				true
+			}
+		}
+	}
+
+	fn next_str_esc(&mut self, c: char, mut buffer: String, mut error: Option<LexError>) -> bool {
+		match c {
+			'n' => buffer.push('\n'),
+			't' => buffer.push('\t'),
+			'r' => buffer.push('\r'),
+			'0' => buffer.push('\0'),
+			'\\' => buffer.push('\\'),
+			'"' => buffer.push('"'),
+			'x' => {
+				self.state = State::StringEscHex(buffer, error, String::new());
+				return true;
+			}
+			'u' => {
+				self.state = State::StringEscUnicodeOpen(buffer, error);
+				return true;
+			}
+			_ => {
+				error.get_or_insert(LexError::MalformedEscapeSequence);
+			}
+		}
+		self.state = State::StringOwned(buffer, error);
+		true
+	}
+
+	fn next_str_hex(&mut self, c: char, mut buffer: String, mut error: Option<LexError>, mut hex: String) -> bool {
+		if hex.len() < 2 && c.is_ascii_hexdigit() {
+			hex.push(c);
+			self.state = State::StringEscHex(buffer, error, hex);
+			return true;
+		}
+		match u8::from_str_radix(&hex, 16) {
+			Ok(byte) if hex.len() == 2 => buffer.push(byte as char),
+			_ => {
+				error.get_or_insert(LexError::MalformedEscapeSequence);
+			}
+		}
+		self.next_str(c, buffer, error)
+	}
+
+	fn next_str_unicode_open(&mut self, c: char, buffer: String, mut error: Option<LexError>) -> bool {
+		if c == '{' {
+			self.state = State::StringEscUnicode(buffer, error, String::new());
+			true
 		} else {
-			match c {
-				'"' => {
-					self.push_inclusive(Lexeme::String(self.text[index].to_owned()));
-					State::Common
+			error.get_or_insert(LexError::MalformedEscapeSequence);
+			self.next_str(c, buffer, error)
+		}
+	}
+
+	fn next_str_unicode(
+		&mut self,
+		c: char,
+		mut buffer: String,
+		mut error: Option<LexError>,
+		mut hex: String,
+	) -> bool {
+		if c == '}' {
+			match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+				Some(decoded) if !hex.is_empty() => buffer.push(decoded),
+				_ => {
+					error.get_or_insert(LexError::MalformedEscapeSequence);
 				}
-				'\\' => State::StringEscSeq(index.start),
-				_ => return true,
 			}
-		};
-		true
+			self.state = State::StringOwned(buffer, error);
+			true
+		} else if hex.len() < 6 && c.is_ascii_hexdigit() {
+			hex.push(c);
+			self.state = State::StringEscUnicode(buffer, error, hex);
+			true
+		} else {
+			error.get_or_insert(LexError::MalformedEscapeSequence);
+			self.next_str(c, buffer, error)
+		}
+	}
+
+	fn next_dot(&mut self, c: char, start: usize) -> bool {
+		if c.is_ascii_digit() {
+			self.state = State::Decimal(start);
+			true
+		} else {
+			self.state = State::Common;
+			self.push_exclusive(Lexeme::SignFullStop)
+		}
 	}
 
 	fn next_num(&mut self, c: char, mut radix: u32, index: Range<usize>) -> bool {
@@ -169,32 +365,77 @@ impl<'l> Lexer<'l> {
 				return true;
 			}
 		}
-		if c.is_digit(radix) {
+		if c.is_digit(radix) || c == '_' {
 			true
 		} else if c == '.' && radix == 10 {
 			self.state = State::Decimal(index.start);
 			true
+		} else if (c == 'e' || c == 'E') && radix == 10 {
+			self.state = State::DecimalExp(index.start);
+			true
 		} else {
 			self.state = State::Common;
-			self.push_exclusive(match u32::from_str_radix(&self.text[index], radix) {
+			self.push_exclusive(match strip_digit_separators(&self.text[index])
+				.and_then(|digits| u32::from_str_radix(&digits, radix).map_err(drop))
+			{
 				Ok(num) => Lexeme::Number32(num),
-				Err(_) => Lexeme::Error,
+				Err(()) => Lexeme::Error(LexError::MalformedNumber),
 			})
 		}
 	}
 
 	fn next_decimal(&mut self, c: char, index: Range<usize>) -> bool {
-		if c.is_digit(10) {
+		if c.is_digit(10) || c == '_' {
+			true
+		} else if c == 'e' || c == 'E' {
+			self.state = State::DecimalExp(index.start);
+			true
+		} else {
+			self.finish_decimal(index)
+		}
+	}
+
+	fn next_decimal_exp(&mut self, c: char, start: usize) -> bool {
+		if c == '+' || c == '-' {
+			self.state = State::DecimalExpSigned(start);
+			true
+		} else if c.is_ascii_digit() {
+			self.state = State::DecimalExpDigits(start);
 			true
 		} else {
 			self.state = State::Common;
-			self.push_exclusive(match self.text[index].parse() {
-				Ok(num) => Lexeme::Decimal64(num),
-				Err(_) => Lexeme::Error,
-			})
+			self.push_exclusive(Lexeme::Error(LexError::MalformedNumber))
 		}
 	}
 
+	fn next_decimal_exp_signed(&mut self, c: char, start: usize) -> bool {
+		if c.is_ascii_digit() {
+			self.state = State::DecimalExpDigits(start);
+			true
+		} else {
+			self.state = State::Common;
+			self.push_exclusive(Lexeme::Error(LexError::MalformedNumber))
+		}
+	}
+
+	fn next_decimal_exp_digits(&mut self, c: char, index: Range<usize>) -> bool {
+		if c.is_digit(10) || c == '_' {
+			true
+		} else {
+			self.finish_decimal(index)
+		}
+	}
+
+	fn finish_decimal(&mut self, index: Range<usize>) -> bool {
+		self.state = State::Common;
+		self.push_exclusive(match strip_digit_separators(&self.text[index])
+			.and_then(|digits| digits.parse().map_err(drop))
+		{
+			Ok(num) => Lexeme::Decimal64(num),
+			Err(()) => Lexeme::Error(LexError::MalformedNumber),
+		})
+	}
+
 	fn next_blk_comment(&mut self, after_asterisk: bool, c: char) -> bool {
 		if after_asterisk {
 			self.state = match c {
@@ -215,8 +456,8 @@ impl<'l> Lexer<'l> {
 		true
 	}
 
-	fn push_inclusive(&mut self, lexeme: Lexeme) -> bool {
-		self.tokens.push(Token::new(
+	fn push_inclusive(&mut self, lexeme: Lexeme<'l>) -> bool {
+		self.pending_out = Some(Token::new(
 			lexeme,
 			self.token_start,
 			Bound::Included(self.pos),
@@ -224,8 +465,8 @@ impl<'l> Lexer<'l> {
 		true
 	}
 
-	fn push_exclusive(&mut self, lexeme: Lexeme) -> bool {
-		self.tokens.push(Token::new(
+	fn push_exclusive(&mut self, lexeme: Lexeme<'l>) -> bool {
+		self.pending_out = Some(Token::new(
 			lexeme,
 			self.token_start,
 			Bound::Excluded(self.pos),
@@ -233,8 +474,8 @@ impl<'l> Lexer<'l> {
 		false
 	}
 
-	fn push_symbol(&mut self, symbol: String) -> bool {
-		self.tokens.push(Token::new(
+	fn push_symbol(&mut self, symbol: &'l str) -> bool {
+		self.pending_out = Some(Token::new(
 			Lexeme::Ident(self.symbols.get_or_create_id(symbol)),
 			self.token_start,
 			Bound::Excluded(self.pos),
@@ -242,18 +483,77 @@ impl<'l> Lexer<'l> {
 		false
 	}
 
-	fn finalize(&mut self) {
-		match self.state {
-			State::Common | State::BlockComment | State::BlockCommentAst | State::LineComment => {
-				false
+	fn finalize(&mut self) -> Token<'l> {
+		if !self.ended {
+			self.ended = true;
+			match self.state {
+				State::Common | State::LineComment => {}
+				State::BlockComment | State::BlockCommentAst => {
+					self.push_exclusive(Lexeme::Error(LexError::UnterminatedBlockComment));
+				}
+				State::Operator(first) => {
+					self.next_op(first, '\0');
+				}
+				State::Dot(start) => {
+					self.next_dot('\0', start);
+				}
+				State::Ident(start) => {
+					self.next_id('\0', start..self.text.len());
+				}
+				State::String(_)
+				| State::StringOwned(..)
+				| State::StringEscSeq(..)
+				| State::StringEscHex(..)
+				| State::StringEscUnicodeOpen(..)
+				| State::StringEscUnicode(..) => {
+					self.push_exclusive(Lexeme::Error(LexError::UnterminatedString));
+				}
+				State::Number(start, radix) => {
+					self.next_num('\0', radix, start..self.text.len());
+				}
+				State::Decimal(start) => {
+					self.next_decimal('\0', start..self.text.len());
+				}
+				State::DecimalExp(start) => {
+					self.next_decimal_exp('\0', start);
+				}
+				State::DecimalExpSigned(start) => {
+					self.next_decimal_exp_signed('\0', start);
+				}
+				State::DecimalExpDigits(start) => {
+					self.next_decimal_exp_digits('\0', start..self.text.len());
+				}
 			}
-			State::Operator(first) => self.next_op(first, '\0'),
-			State::Ident(start) => self.next_id('\0', start..self.text.len()),
-			State::String(_) | State::StringEscSeq(_) => self.push_exclusive(Lexeme::Error),
-			State::Number(start, radix) => self.next_num('\0', radix, start..self.text.len()),
-			State::Decimal(start) => self.next_decimal('\0', start..self.text.len()),
-		};
+			if let Some(token) = self.pending_out.take() {
+				return token;
+			}
+		}
 		self.token_start = self.pos;
 		self.push_exclusive(Lexeme::Eof);
+		self.pending_out.take().unwrap()
+	}
+}
+
+impl<'l> Iterator for Lexer<'l> {
+	type Item = Token<'l>;
+
+	fn next(&mut self) -> Option<Token<'l>> {
+		if self.done {
+			return None;
+		}
+		let token = self.next_token();
+		if matches!(token.lexeme(), Lexeme::Eof) {
+			self.done = true;
+		}
+		Some(token)
+	}
+}
+
+/// Strips `_` digit separators from a numeric literal slice, rejecting a
+/// leading, trailing, or doubled separator.
+fn strip_digit_separators(raw: &str) -> Result<String, ()> {
+	if raw.starts_with('_') || raw.ends_with('_') || raw.contains("__") {
+		return Err(());
 	}
+	Ok(raw.chars().filter(|&c| c != '_').collect())
 }
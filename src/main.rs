@@ -1,6 +1,8 @@
+mod ast;
 mod lang;
 mod lexer;
-use lexer::Lexer;
+mod parser;
+use parser::Parser;
 use std::io::Read;
 
 fn main() {
@@ -9,5 +11,11 @@ fn main() {
 		eprintln!("Error: {}", err);
 		std::process::exit(1);
 	}
-	println!("{:#?}", Lexer::parse(&program));
+	match Parser::parse(&program) {
+		Ok(ast) => println!("{:#?}", ast),
+		Err(err) => {
+			eprintln!("Parse error at {:?}: {}", err.start, err.message);
+			std::process::exit(1);
+		}
+	}
 }
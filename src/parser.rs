@@ -0,0 +1,269 @@
+use super::ast::{
+	AddOp, Ast, Block, CompareOp, Condition, ConstDecl, Expression, Factor, Literal, MulOp, Program,
+	ProcedureDecl, Statement, Term,
+};
+use super::lang::{Lexeme, Position, Token};
+use super::lexer::Lexer;
+use std::ops::Bound;
+
+/// A recursive-descent parser that drives a `Lexer` token-at-a-time and
+/// builds a PL/0 AST. Unlike the lexer, a single malformed construct stops
+/// the parse: there is no sensible tree to keep building once the grammar
+/// is violated, so the first error is returned with the span of the token
+/// that triggered it.
+pub struct Parser<'l> {
+	lexer: Lexer<'l>,
+	current: Token<'l>,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+	pub message: String,
+	pub start: Position,
+	pub end: Bound<Position>,
+}
+
+impl<'l> Parser<'l> {
+	pub fn new(text: &'l str) -> Self {
+		let mut lexer = Lexer::new(text);
+		let current = lexer.next_token();
+		Self { lexer, current }
+	}
+
+	pub fn parse(text: &'l str) -> Result<Ast<'l>, ParseError> {
+		let mut parser = Self::new(text);
+		let program = parser.parse_program()?;
+		Ok(Ast::new(program, parser.lexer.into_symbol_table()))
+	}
+
+	fn bump(&mut self) -> Token<'l> {
+		std::mem::replace(&mut self.current, self.lexer.next_token())
+	}
+
+	fn error(&self, message: impl Into<String>) -> ParseError {
+		ParseError {
+			message: message.into(),
+			start: self.current.start(),
+			end: self.current.end(),
+		}
+	}
+
+	fn eat(&mut self, matches: impl Fn(&Lexeme) -> bool, what: &str) -> Result<Token<'l>, ParseError> {
+		if matches(self.current.lexeme()) {
+			Ok(self.bump())
+		} else {
+			Err(self.error(format!("expected {what}")))
+		}
+	}
+
+	fn expect_ident(&mut self) -> Result<usize, ParseError> {
+		match self.current.lexeme() {
+			Lexeme::Ident(id) => {
+				let id = *id;
+				self.bump();
+				Ok(id)
+			}
+			_ => Err(self.error("expected an identifier")),
+		}
+	}
+
+	fn expect_literal(&mut self) -> Result<Literal, ParseError> {
+		match self.current.lexeme() {
+			Lexeme::Number32(n) => {
+				let n = *n;
+				self.bump();
+				Ok(Literal::Int(n))
+			}
+			Lexeme::Decimal64(n) => {
+				let n = *n;
+				self.bump();
+				Ok(Literal::Decimal(n))
+			}
+			_ => Err(self.error("expected a number")),
+		}
+	}
+
+	fn parse_program(&mut self) -> Result<Program, ParseError> {
+		let block = self.parse_block()?;
+		self.eat(|l| matches!(l, Lexeme::SignFullStop), "'.'")?;
+		Ok(Program { block })
+	}
+
+	fn parse_block(&mut self) -> Result<Block, ParseError> {
+		let mut consts = Vec::new();
+		if matches!(self.current.lexeme(), Lexeme::KwConst) {
+			self.bump();
+			loop {
+				let name = self.expect_ident()?;
+				self.eat(|l| matches!(l, Lexeme::SignEquals), "'='")?;
+				let value = self.expect_literal()?;
+				consts.push(ConstDecl { name, value });
+				if matches!(self.current.lexeme(), Lexeme::SignComma) {
+					self.bump();
+					continue;
+				}
+				break;
+			}
+			self.eat(|l| matches!(l, Lexeme::SignSemicolon), "';'")?;
+		}
+
+		let mut vars = Vec::new();
+		if matches!(self.current.lexeme(), Lexeme::KwVar) {
+			self.bump();
+			loop {
+				vars.push(self.expect_ident()?);
+				if matches!(self.current.lexeme(), Lexeme::SignComma) {
+					self.bump();
+					continue;
+				}
+				break;
+			}
+			self.eat(|l| matches!(l, Lexeme::SignSemicolon), "';'")?;
+		}
+
+		let mut procedures = Vec::new();
+		while matches!(self.current.lexeme(), Lexeme::KwProcedure) {
+			self.bump();
+			let name = self.expect_ident()?;
+			self.eat(|l| matches!(l, Lexeme::SignSemicolon), "';'")?;
+			let block = Box::new(self.parse_block()?);
+			self.eat(|l| matches!(l, Lexeme::SignSemicolon), "';'")?;
+			procedures.push(ProcedureDecl { name, block });
+		}
+
+		let statement = self.parse_statement()?;
+		Ok(Block { consts, vars, procedures, statement })
+	}
+
+	fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+		match self.current.lexeme() {
+			Lexeme::Ident(_) => {
+				let name = self.expect_ident()?;
+				self.eat(|l| matches!(l, Lexeme::OpAssign), "':='")?;
+				let value = self.parse_expression()?;
+				Ok(Statement::Assign { name, value })
+			}
+			Lexeme::KwCall => {
+				self.bump();
+				let name = self.expect_ident()?;
+				Ok(Statement::Call { name })
+			}
+			Lexeme::KwBegin => {
+				self.bump();
+				let mut statements = vec![self.parse_statement()?];
+				while matches!(self.current.lexeme(), Lexeme::SignSemicolon) {
+					self.bump();
+					statements.push(self.parse_statement()?);
+				}
+				self.eat(|l| matches!(l, Lexeme::KwEnd), "'end'")?;
+				Ok(Statement::Begin(statements))
+			}
+			Lexeme::KwIf => {
+				self.bump();
+				let condition = self.parse_condition()?;
+				self.eat(|l| matches!(l, Lexeme::KwThen), "'then'")?;
+				let then_branch = Box::new(self.parse_statement()?);
+				Ok(Statement::If { condition, then_branch })
+			}
+			Lexeme::KwWhile => {
+				self.bump();
+				let condition = self.parse_condition()?;
+				self.eat(|l| matches!(l, Lexeme::KwDo), "'do'")?;
+				let body = Box::new(self.parse_statement()?);
+				Ok(Statement::While { condition, body })
+			}
+			Lexeme::SignQuestion => {
+				self.bump();
+				let name = self.expect_ident()?;
+				Ok(Statement::Read { name })
+			}
+			Lexeme::SignExclamation => {
+				self.bump();
+				let value = self.parse_expression()?;
+				Ok(Statement::Write { value })
+			}
+			_ => Ok(Statement::Empty),
+		}
+	}
+
+	fn parse_condition(&mut self) -> Result<Condition, ParseError> {
+		if matches!(self.current.lexeme(), Lexeme::KwOdd) {
+			self.bump();
+			let value = self.parse_expression()?;
+			Ok(Condition::Odd(value))
+		} else {
+			let lhs = self.parse_expression()?;
+			let op = match self.current.lexeme() {
+				Lexeme::SignEquals => CompareOp::Equal,
+				Lexeme::SignNumber => CompareOp::NotEqual,
+				Lexeme::OpLess => CompareOp::Less,
+				Lexeme::OpLessEqual => CompareOp::LessEqual,
+				Lexeme::OpGreater => CompareOp::Greater,
+				Lexeme::OpGreaterEqual => CompareOp::GreaterEqual,
+				_ => return Err(self.error("expected a comparison operator")),
+			};
+			self.bump();
+			let rhs = self.parse_expression()?;
+			Ok(Condition::Compare { lhs, op, rhs })
+		}
+	}
+
+	fn parse_expression(&mut self) -> Result<Expression, ParseError> {
+		let negate = match self.current.lexeme() {
+			Lexeme::SignPlus => {
+				self.bump();
+				false
+			}
+			Lexeme::SignMinus => {
+				self.bump();
+				true
+			}
+			_ => false,
+		};
+		let first = self.parse_term()?;
+		let mut rest = Vec::new();
+		loop {
+			let op = match self.current.lexeme() {
+				Lexeme::SignPlus => AddOp::Add,
+				Lexeme::SignMinus => AddOp::Sub,
+				_ => break,
+			};
+			self.bump();
+			rest.push((op, self.parse_term()?));
+		}
+		Ok(Expression { negate, first, rest })
+	}
+
+	fn parse_term(&mut self) -> Result<Term, ParseError> {
+		let first = self.parse_factor()?;
+		let mut rest = Vec::new();
+		loop {
+			let op = match self.current.lexeme() {
+				Lexeme::SignAsterisk => MulOp::Mul,
+				Lexeme::OpSolidus => MulOp::Div,
+				_ => break,
+			};
+			self.bump();
+			rest.push((op, self.parse_factor()?));
+		}
+		Ok(Term { first, rest })
+	}
+
+	fn parse_factor(&mut self) -> Result<Factor, ParseError> {
+		match self.current.lexeme() {
+			Lexeme::Ident(id) => {
+				let id = *id;
+				self.bump();
+				Ok(Factor::Ident(id))
+			}
+			Lexeme::Number32(_) | Lexeme::Decimal64(_) => Ok(Factor::Literal(self.expect_literal()?)),
+			Lexeme::SignLParen => {
+				self.bump();
+				let expr = self.parse_expression()?;
+				self.eat(|l| matches!(l, Lexeme::SignRParen), "')'")?;
+				Ok(Factor::Paren(Box::new(expr)))
+			}
+			_ => Err(self.error("expected an identifier, a number, or '('")),
+		}
+	}
+}